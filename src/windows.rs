@@ -0,0 +1,155 @@
+use winreg::enums::{RegType, HKEY_CURRENT_USER, KEY_SET_VALUE};
+use winreg::{RegKey, RegValue};
+
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::ptr;
+
+pub fn get<'a, T: fmt::Display>(var: T) -> io::Result<String> {
+    let key = RegKey::predef(HKEY_CURRENT_USER).open_subkey("Environment")?;
+    Ok(key.get_value::<String, String>(var.to_string())?.to_string())
+}
+
+/// Like [`get`] but returns the raw [`OsString`], read straight from the
+/// registry's wide-character value, so a value that isn't valid Unicode
+/// can still be round-tripped instead of erroring out.
+pub fn get_os<T: AsRef<OsStr>>(var: T) -> io::Result<OsString> {
+    let key = RegKey::predef(HKEY_CURRENT_USER).open_subkey("Environment")?;
+    let raw = key.get_raw_value(var.as_ref().to_string_lossy().as_ref())?;
+    let wide: Vec<u16> = raw
+        .bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|&word| word != 0)
+        .collect();
+    Ok(OsString::from_wide(&wide))
+}
+
+/// Appends a value to an environment variable
+/// Useful for appending a value to PATH
+pub fn append<T: fmt::Display>(var: T, value: T) -> io::Result<()> {
+    append_os(var.to_string(), value.to_string())
+}
+
+/// Like [`append`] but takes the value as raw bytes via [`AsRef<OsStr>`].
+pub fn append_os<T: AsRef<OsStr>>(var: T, value: T) -> io::Result<()> {
+    splice_os(var, value, true)
+}
+
+/// Prepends a value to an environment variable
+/// Useful for prepending a value to PATH
+pub fn prepend<T: fmt::Display>(var: T, value: T) -> io::Result<()> {
+    prepend_os(var.to_string(), value.to_string())
+}
+
+/// Like [`prepend`] but takes the value as raw bytes via [`AsRef<OsStr>`].
+pub fn prepend_os<T: AsRef<OsStr>>(var: T, value: T) -> io::Result<()> {
+    splice_os(var, value, false)
+}
+
+fn splice_os<T: AsRef<OsStr>>(var: T, value: T, append: bool) -> io::Result<()> {
+    let var = var.as_ref();
+    let value = value.as_ref();
+    let current = get_os(var).unwrap_or_default();
+    let mut combined = OsString::new();
+    if append {
+        combined.push(value);
+        if !current.is_empty() {
+            combined.push(";");
+            combined.push(&current);
+        }
+    } else {
+        if !current.is_empty() {
+            combined.push(&current);
+            combined.push(";");
+        }
+        combined.push(value);
+    }
+    set_os(var, combined)
+}
+
+/// Sets an environment variable without checking
+/// if it exists.
+/// If it does you will override the value.
+pub fn set<T: fmt::Display, U: fmt::Display>(var: T, value: U) -> io::Result<()> {
+    set_os(var.to_string(), value.to_string())
+}
+
+/// Like [`set`] but takes the value as raw bytes via [`AsRef<OsStr>`], so
+/// values that aren't valid Unicode can be persisted too. Writes
+/// straight into the HKCU `Environment` key and broadcasts
+/// `WM_SETTINGCHANGE` so already-running processes pick up the change
+/// without needing a reboot or a fresh PowerShell session.
+pub fn set_os<T, U>(var: T, value: U) -> io::Result<()>
+where
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+{
+    let key = RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey_with_flags("Environment", KEY_SET_VALUE)?;
+    key.set_raw_value(
+        var.as_ref().to_string_lossy().as_ref(),
+        &reg_value_for(value.as_ref()),
+    )?;
+    broadcast_environment_change();
+    Ok(())
+}
+
+/// Removes a variable previously set with `set`/`append`/`prepend`,
+/// deleting it from the HKCU `Environment` key that [`get`] reads from.
+/// A no-op if `var` was never set.
+pub fn remove<T: fmt::Display>(var: T) -> io::Result<()> {
+    let key = RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey_with_flags("Environment", KEY_SET_VALUE)?;
+    match key.delete_value(var.to_string()) {
+        Ok(()) => {
+            broadcast_environment_change();
+            Ok(())
+        }
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// `PATH`-style values often reference another variable, e.g.
+/// `%SystemRoot%\system32`. Those need `REG_EXPAND_SZ` so Explorer and
+/// other readers expand them; anything else is a plain `REG_SZ`.
+fn reg_value_for(value: &OsStr) -> RegValue {
+    let mut wide: Vec<u16> = value.encode_wide().collect();
+    wide.push(0);
+    let bytes = wide.iter().flat_map(|word| word.to_le_bytes()).collect();
+    let vtype = if value.to_string_lossy().contains('%') {
+        RegType::REG_EXPAND_SZ
+    } else {
+        RegType::REG_SZ
+    };
+    RegValue { bytes, vtype }
+}
+
+/// Broadcasts `WM_SETTINGCHANGE` the way Control Panel does after
+/// editing environment variables, so already-running Explorer/shell
+/// processes notice the registry change without a reboot.
+fn broadcast_environment_change() {
+    use winapi::shared::minwindef::LPARAM;
+    use winapi::um::winuser::{
+        SendMessageTimeoutW, HWND_BROADCAST, SMTO_ABORTIFHUNG, WM_SETTINGCHANGE,
+    };
+
+    let param: Vec<u16> = OsStr::new("Environment")
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    unsafe {
+        SendMessageTimeoutW(
+            HWND_BROADCAST,
+            WM_SETTINGCHANGE,
+            0,
+            param.as_ptr() as LPARAM,
+            SMTO_ABORTIFHUNG,
+            5000,
+            ptr::null_mut(),
+        );
+    }
+}